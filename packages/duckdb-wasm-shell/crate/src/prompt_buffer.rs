@@ -1,12 +1,295 @@
 use crate::vt100;
 use crate::xterm::Terminal;
 use ropey::Rope;
+use std::collections::VecDeque;
 use std::fmt::Write;
+use unicode_segmentation::UnicodeSegmentation;
 use web_sys::KeyboardEvent;
 
 const PROMPT_INIT: &'static str = "duckdb> ";
 const PROMPT_CONT: &'static str = "   ...> ";
 const PROMPT_WIDTH: usize = 8;
+/// Maximum number of statements kept in the history ring
+const HISTORY_LIMIT: usize = 1000;
+/// Status line shown while a reverse-incremental search is active
+const SEARCH_PROMPT: &'static str = "(reverse-i-search)`";
+
+/// SQL keywords offered by the default completer
+const SQL_KEYWORDS: &[&str] = &[
+    "SELECT", "FROM", "WHERE", "GROUP", "ORDER", "BY", "HAVING", "LIMIT", "OFFSET", "JOIN",
+    "LEFT", "RIGHT", "INNER", "OUTER", "FULL", "ON", "AS", "DISTINCT", "INSERT", "INTO", "VALUES",
+    "UPDATE", "SET", "DELETE", "CREATE", "TABLE", "VIEW", "DROP", "ALTER", "INDEX", "AND", "OR",
+    "NOT", "NULL", "IS", "IN", "EXISTS", "BETWEEN", "LIKE", "UNION", "ALL", "EXCEPT", "INTERSECT",
+    "WITH", "CASE", "WHEN", "THEN", "ELSE", "END", "DESC", "ASC", "COUNT", "SUM", "AVG", "MIN",
+    "MAX",
+];
+
+/// Returns the candidates that complete a SQL statement at a given cursor position.
+///
+/// Implementations may offer keywords, or consult an external source such as the
+/// DuckDB catalog for table and column names.
+pub trait Completer {
+    /// Returns the byte offset where the replacement should start, and the candidates
+    /// that complete the token under the cursor.
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>);
+}
+
+/// Completes SQL keywords and, if a catalog lookup is configured, table and column names.
+pub struct DefaultCompleter {
+    /// Looks up table and column names matching a prefix in the DuckDB catalog
+    catalog: Option<Box<dyn Fn(&str) -> Vec<String>>>,
+}
+
+impl DefaultCompleter {
+    /// Construct a completer that only offers SQL keywords
+    pub fn new() -> Self {
+        Self { catalog: None }
+    }
+
+    /// Construct a completer that also consults the DuckDB catalog for the token prefix
+    pub fn with_catalog(catalog: Box<dyn Fn(&str) -> Vec<String>>) -> Self {
+        Self {
+            catalog: Some(catalog),
+        }
+    }
+}
+
+impl Completer for DefaultCompleter {
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let start = word_start(line, pos);
+        let prefix = &line[start..pos];
+        let prefix_upper = prefix.to_uppercase();
+        let mut candidates: Vec<String> = SQL_KEYWORDS
+            .iter()
+            .filter(|kw| kw.starts_with(&prefix_upper))
+            .map(|kw| kw.to_string())
+            .collect();
+        if let Some(catalog) = &self.catalog {
+            candidates.extend(catalog(prefix));
+        }
+        (start, candidates)
+    }
+}
+
+/// Find the byte offset where the identifier under `pos` starts by walking back
+/// over alphanumeric and underscore characters.
+fn word_start(line: &str, pos: usize) -> usize {
+    line[..pos]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(0)
+}
+
+/// Compute the longest common prefix shared by all candidates, comparing character by character.
+fn longest_common_prefix(candidates: &[String]) -> String {
+    let mut prefix = String::new();
+    let mut iters: Vec<_> = candidates.iter().map(|c| c.chars()).collect();
+    'outer: loop {
+        let mut next: Option<char> = None;
+        for iter in iters.iter_mut() {
+            match iter.next() {
+                Some(c) => match next {
+                    None => next = Some(c),
+                    Some(expected) if expected == c => {}
+                    _ => break 'outer,
+                },
+                None => break 'outer,
+            }
+        }
+        match next {
+            Some(c) => prefix.push(c),
+            None => break,
+        }
+    }
+    prefix
+}
+
+/// Styles a statement for display, e.g. by coloring SQL keywords, string literals,
+/// numbers, and comments with vt100 SGR codes.
+pub trait Highlighter {
+    /// Returns `line`, styled for display. `pos` is the current cursor offset (in chars)
+    /// into `line`, for highlighters that style the token under the cursor differently.
+    fn highlight(&self, line: &str, pos: usize) -> String;
+}
+
+/// Colors SQL keywords, string literals, numbers, and line comments.
+pub struct SqlHighlighter;
+
+impl Highlighter for SqlHighlighter {
+    fn highlight(&self, line: &str, _pos: usize) -> String {
+        let len = line.len();
+        let mut out = String::new();
+        let mut i = 0;
+        while i < len {
+            let c = line[i..].chars().next().unwrap();
+            if line[i..].starts_with("--") {
+                let end = line[i..]
+                    .find(|ch| ch == '\n' || ch == vt100::PARAGRAPH_SEPERATOR)
+                    .map(|o| i + o)
+                    .unwrap_or(len);
+                write!(
+                    out,
+                    "{sgr}{text}{reset}",
+                    sgr = vt100::SGR_COMMENT,
+                    text = &line[i..end],
+                    reset = vt100::SGR_RESET
+                )
+                .unwrap();
+                i = end;
+            } else if c == '\'' {
+                let mut end = i + c.len_utf8();
+                while end < len {
+                    let nc = line[end..].chars().next().unwrap();
+                    end += nc.len_utf8();
+                    if nc == '\'' {
+                        break;
+                    }
+                }
+                write!(
+                    out,
+                    "{sgr}{text}{reset}",
+                    sgr = vt100::SGR_STRING,
+                    text = &line[i..end],
+                    reset = vt100::SGR_RESET
+                )
+                .unwrap();
+                i = end;
+            } else if c.is_ascii_digit() {
+                let mut end = i;
+                while end < len {
+                    let nc = line[end..].chars().next().unwrap();
+                    if nc.is_ascii_digit() || nc == '.' {
+                        end += nc.len_utf8();
+                    } else {
+                        break;
+                    }
+                }
+                write!(
+                    out,
+                    "{sgr}{text}{reset}",
+                    sgr = vt100::SGR_NUMBER,
+                    text = &line[i..end],
+                    reset = vt100::SGR_RESET
+                )
+                .unwrap();
+                i = end;
+            } else if c.is_alphabetic() || c == '_' {
+                let mut end = i;
+                while end < len {
+                    let nc = line[end..].chars().next().unwrap();
+                    if nc.is_alphanumeric() || nc == '_' {
+                        end += nc.len_utf8();
+                    } else {
+                        break;
+                    }
+                }
+                let word = &line[i..end];
+                if SQL_KEYWORDS.contains(&word.to_uppercase().as_str()) {
+                    write!(
+                        out,
+                        "{sgr}{text}{reset}",
+                        sgr = vt100::SGR_KEYWORD,
+                        text = word,
+                        reset = vt100::SGR_RESET
+                    )
+                    .unwrap();
+                } else {
+                    out.push_str(word);
+                }
+                i = end;
+            } else {
+                out.push(c);
+                i += c.len_utf8();
+            }
+        }
+        out
+    }
+}
+
+/// Maximum number of edit records kept on the undo stack
+const UNDO_LIMIT: usize = 200;
+
+/// A reversible edit against `text_buffer`.
+enum EditRecord {
+    /// One or more contiguous characters inserted at `pos`
+    Insert { pos: usize, text: String },
+    /// A span of text that was removed from `pos`
+    Delete { pos: usize, text: String },
+    /// A whole-buffer swap performed by a compound operation (completion, yank,
+    /// history recall, search) so it can be undone as a single atomic step
+    Replace {
+        old: String,
+        old_cursor: usize,
+        new: String,
+        new_cursor: usize,
+    },
+}
+
+/// Read-only session state passed to a `Hinter`.
+pub struct HintContext<'a> {
+    /// Previously submitted statements, oldest first
+    pub history: &'a VecDeque<String>,
+}
+
+/// Suggests how the current input might continue, shown inline as a dim hint.
+pub trait Hinter {
+    /// Returns the suggested continuation of `line` at cursor `pos` (in chars), if any.
+    fn hint(&self, line: &str, pos: usize, ctx: &HintContext) -> Option<String>;
+}
+
+/// Hints the remaining suffix of the newest history entry that starts with the current input.
+pub struct HistoryHinter;
+
+impl Hinter for HistoryHinter {
+    fn hint(&self, line: &str, pos: usize, ctx: &HintContext) -> Option<String> {
+        if line.is_empty() || pos != line.chars().count() {
+            return None;
+        }
+        ctx.history
+            .iter()
+            .rev()
+            .find(|entry| entry.len() > line.len() && entry.starts_with(line))
+            .map(|entry| entry[line.len()..].to_string())
+    }
+}
+
+/// Maximum number of entries kept in the kill ring
+const KILL_RING_LIMIT: usize = 60;
+
+/// The direction a kill command removed text in, used to decide whether consecutive
+/// kills should append to the same kill ring entry.
+#[derive(Clone, Copy, PartialEq)]
+enum KillDirection {
+    Backward,
+    Forward,
+}
+
+/// Map a char offset to the byte offset of the same position in `text`.
+fn char_to_byte(text: &str, char_idx: usize) -> usize {
+    text.char_indices()
+        .nth(char_idx)
+        .map(|(i, _)| i)
+        .unwrap_or_else(|| text.len())
+}
+
+/// Map a byte offset to the char offset of the same position in `text`.
+fn byte_to_char(text: &str, byte_idx: usize) -> usize {
+    text[..byte_idx].chars().count()
+}
+
+/// Tracks an in-progress Tab completion so repeated Tab presses cycle the candidates.
+struct CompletionState {
+    /// Char offset where the completed token starts
+    start: usize,
+    /// All candidates for the token under the cursor
+    candidates: Vec<String>,
+    /// Index of the candidate currently inserted
+    index: usize,
+    /// Number of characters currently inserted for the candidate at `index`
+    inserted_len: usize,
+}
 
 pub struct PromptBuffer {
     /// The pending output buffer
@@ -15,8 +298,54 @@ pub struct PromptBuffer {
     text_buffer: Rope,
     /// The iterator
     cursor: usize,
+    /// The physical terminal row the cursor was left on by the last repaint, i.e. how many
+    /// rows `refresh_line` must move up before erasing and redrawing. This is independent of
+    /// `char_to_line(self.cursor)`, which already reflects the *post-edit* logical position.
+    cursor_row: usize,
     /// The terminal width
     terminal_width: usize,
+    /// Previously submitted statements, oldest first
+    history: VecDeque<String>,
+    /// Cursor into `history`. `history.len()` means "not browsing, editing a fresh line"
+    history_index: usize,
+    /// The line that was being edited before history browsing started
+    history_end: Option<String>,
+    /// Whether a reverse-incremental search is currently active
+    search_active: bool,
+    /// The query typed since `Ctrl+R` was pressed
+    search_query: String,
+    /// The text and cursor to restore if the search is cancelled
+    search_origin: Option<(String, usize)>,
+    /// The history index of the match currently shown, so repeated `Ctrl+R` can continue further back
+    search_match: Option<usize>,
+    /// Whether `search_render` has already drawn the single-line status prompt,
+    /// so later repaints rewind in place instead of walking up from the untouched buffer cursor
+    search_painted: bool,
+    /// The completer consulted on Tab
+    completer: Box<dyn Completer>,
+    /// The in-progress completion, if Tab was pressed and multiple candidates remain
+    completion_state: Option<CompletionState>,
+    /// Killed spans, oldest first
+    kill_ring: VecDeque<String>,
+    /// Direction of the most recent kill, so consecutive kills in the same direction merge
+    last_kill_dir: Option<KillDirection>,
+    /// Offset from the back of `kill_ring` of the entry last yanked
+    yank_pointer: usize,
+    /// The char range of the text last inserted by `Ctrl+Y`, so `Alt+Y` can replace it
+    last_yank: Option<(usize, usize)>,
+    /// Styles the statement text on every repaint
+    highlighter: Box<dyn Highlighter>,
+    /// Suggests inline autocompletion hints, recomputed on every repaint
+    hinter: Box<dyn Hinter>,
+    /// The hint currently painted after the cursor, if any
+    hint: Option<String>,
+    /// Reversible edits, oldest first
+    undo_stack: Vec<EditRecord>,
+    /// Edits undone, so `redo` can reapply them; cleared on every fresh edit
+    redo_stack: Vec<EditRecord>,
+    /// Whether `insert_char`/`remove_range` should push undo records.
+    /// Disabled while a compound operation records a single atomic step itself.
+    undo_recording: bool,
 }
 
 impl PromptBuffer {
@@ -26,7 +355,28 @@ impl PromptBuffer {
             output_buffer: String::new(),
             text_buffer: Rope::new(),
             cursor: 0,
+            cursor_row: 0,
             terminal_width: 0,
+            history: VecDeque::new(),
+            history_index: 0,
+            history_end: None,
+            search_active: false,
+            search_query: String::new(),
+            search_origin: None,
+            search_match: None,
+            search_painted: false,
+            completer: Box::new(DefaultCompleter::new()),
+            completion_state: None,
+            kill_ring: VecDeque::new(),
+            last_kill_dir: None,
+            yank_pointer: 0,
+            last_yank: None,
+            highlighter: Box::new(SqlHighlighter),
+            hinter: Box::new(HistoryHinter),
+            hint: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            undo_recording: true,
         }
     }
 
@@ -35,6 +385,21 @@ impl PromptBuffer {
         self.terminal_width = term.get_cols() as usize;
     }
 
+    /// Replace the completer used on Tab, e.g. to wire up a DuckDB catalog lookup
+    pub fn set_completer(&mut self, completer: Box<dyn Completer>) {
+        self.completer = completer;
+    }
+
+    /// Replace the highlighter used on every repaint
+    pub fn set_highlighter(&mut self, highlighter: Box<dyn Highlighter>) {
+        self.highlighter = highlighter;
+    }
+
+    /// Replace the hinter consulted on every repaint
+    pub fn set_hinter(&mut self, hinter: Box<dyn Hinter>) {
+        self.hinter = hinter;
+    }
+
     /// Flush output buffer to the terminal
     pub fn flush(&mut self, term: &Terminal) {
         term.write(&self.output_buffer);
@@ -52,6 +417,7 @@ impl PromptBuffer {
                 c => c,
             })
             .collect();
+        self.push_history(buffer.clone());
         buffer
     }
 
@@ -60,43 +426,647 @@ impl PromptBuffer {
         self.output_buffer.clear();
         self.text_buffer = Rope::new();
         self.cursor = 0;
+        self.cursor_row = 0;
+        self.history_index = self.history.len();
+        self.history_end = None;
+        self.search_active = false;
+        self.search_query.clear();
+        self.search_origin = None;
+        self.search_match = None;
+        self.search_painted = false;
+        self.completion_state = None;
+        self.last_kill_dir = None;
+        self.yank_pointer = 0;
+        self.last_yank = None;
+        self.hint = None;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
         write!(self.output_buffer, "{}", PROMPT_INIT).unwrap();
     }
 
-    /// Insert a newline at the cursor.
-    /// Writes the prompt continuation string.
-    fn insert_newline(&mut self) {
-        self.text_buffer.insert_char(self.cursor, '\n');
+    /// Recompute the inline autosuggestion hint for the current buffer and cursor.
+    fn update_hint(&mut self) {
+        let text = self.collect_current();
+        let ctx = HintContext {
+            history: &self.history,
+        };
+        self.hint = self.hinter.hint(&text, self.cursor, &ctx);
+    }
+
+    /// Accept the current hint into the buffer, if the cursor is at the end of the input.
+    /// Returns whether a hint was accepted.
+    fn accept_hint(&mut self) -> bool {
+        if self.cursor != self.text_buffer.len_chars() {
+            return false;
+        }
+        let hint = match self.hint.take() {
+            Some(hint) => hint,
+            None => return false,
+        };
+        for c in hint.chars() {
+            self.insert_char(c);
+        }
+        true
+    }
+
+    /// Remove the characters in `start..end` (char offsets) and place the cursor at `start`.
+    fn remove_range(&mut self, start: usize, end: usize) {
+        if self.undo_recording && start < end {
+            let removed: String = self.text_buffer.slice(start..end).chars().collect();
+            self.push_undo_delete(start, removed);
+        }
+        self.text_buffer.remove(start..end);
+        self.cursor = start;
+    }
+
+    /// Record a single inserted character, coalescing it into the previous record
+    /// while the run stays within one word (a non-word character, or a jump in
+    /// position, always starts a fresh record).
+    fn push_undo_insert(&mut self, pos: usize, c: char) {
+        self.redo_stack.clear();
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        if let Some(EditRecord::Insert {
+            pos: last_pos,
+            text,
+        }) = self.undo_stack.last_mut()
+        {
+            let contiguous = *last_pos + text.chars().count() == pos;
+            let last_is_word = text.chars().last().map(is_word).unwrap_or(false);
+            if contiguous && last_is_word && is_word(c) {
+                text.push(c);
+                return;
+            }
+        }
+        self.undo_stack.push(EditRecord::Insert {
+            pos,
+            text: c.to_string(),
+        });
+        while self.undo_stack.len() > UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    /// Record a deleted span as its own undo step.
+    fn push_undo_delete(&mut self, pos: usize, text: String) {
+        self.redo_stack.clear();
+        self.undo_stack.push(EditRecord::Delete { pos, text });
+        while self.undo_stack.len() > UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    /// Run `f`, recording the whole-buffer change it makes as a single undo step.
+    /// Used by compound operations (completion, yank, history recall, search) so
+    /// they can be undone atomically regardless of how many characters they touch.
+    fn record_atomic<F: FnOnce(&mut Self)>(&mut self, f: F) {
+        let old_cursor = self.cursor;
+        let old_text: String = self.text_buffer.chars().collect();
+        self.undo_recording = false;
+        f(self);
+        self.undo_recording = true;
+        let new_text: String = self.text_buffer.chars().collect();
+        if new_text == old_text {
+            return;
+        }
+        self.redo_stack.clear();
+        self.undo_stack.push(EditRecord::Replace {
+            old: old_text,
+            old_cursor,
+            new: new_text,
+            new_cursor: self.cursor,
+        });
+        while self.undo_stack.len() > UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    /// Pop the undo stack, apply the inverse edit, and repaint (`Ctrl+_`/`Ctrl+Z`).
+    fn undo(&mut self) {
+        let record = match self.undo_stack.pop() {
+            Some(record) => record,
+            None => return,
+        };
+        match &record {
+            EditRecord::Insert { pos, text } => {
+                let end = pos + text.chars().count();
+                self.text_buffer.remove(*pos..end);
+                self.cursor = *pos;
+            }
+            EditRecord::Delete { pos, text } => {
+                self.text_buffer.insert(*pos, text);
+                self.cursor = pos + text.chars().count();
+            }
+            EditRecord::Replace {
+                old, old_cursor, ..
+            } => {
+                self.text_buffer = Rope::from_str(old);
+                self.cursor = *old_cursor;
+            }
+        }
+        self.redo_stack.push(record);
+        self.refresh_line();
+    }
+
+    /// Pop the redo stack and reapply the edit it undid.
+    fn redo(&mut self) {
+        let record = match self.redo_stack.pop() {
+            Some(record) => record,
+            None => return,
+        };
+        match &record {
+            EditRecord::Insert { pos, text } => {
+                self.text_buffer.insert(*pos, text);
+                self.cursor = pos + text.chars().count();
+            }
+            EditRecord::Delete { pos, text } => {
+                let end = pos + text.chars().count();
+                self.text_buffer.remove(*pos..end);
+                self.cursor = *pos;
+            }
+            EditRecord::Replace {
+                new, new_cursor, ..
+            } => {
+                self.text_buffer = Rope::from_str(new);
+                self.cursor = *new_cursor;
+            }
+        }
+        self.undo_stack.push(record);
+        self.refresh_line();
+    }
+
+    /// Run completion for the token under the cursor, or cycle to the next candidate
+    /// if Tab was just pressed and several candidates remain.
+    fn complete(&mut self) {
+        if let Some(mut state) = self.completion_state.take() {
+            if state.candidates.len() > 1 {
+                let end = state.start + state.inserted_len;
+                // The first cycling Tab follows an inserted longest-common-prefix, not a full
+                // candidate, so it should offer `candidates[0]` rather than skip straight past
+                // it to `candidates[1]`. Only advance once the text on screen already matches
+                // the candidate at `index`, i.e. a previous Tab actually inserted it.
+                let currently_inserted: String =
+                    self.text_buffer.slice(state.start..end).chars().collect();
+                if currently_inserted == state.candidates[state.index] {
+                    state.index = (state.index + 1) % state.candidates.len();
+                }
+                let candidate = state.candidates[state.index].clone();
+                let inserted_len = candidate.chars().count();
+                self.record_atomic(|buf| {
+                    buf.remove_range(state.start, end);
+                    for c in candidate.chars() {
+                        buf.insert_char(c);
+                    }
+                });
+                state.inserted_len = inserted_len;
+                self.completion_state = Some(state);
+            }
+            return;
+        }
+
+        let line = self.collect_current();
+        let pos = line
+            .char_indices()
+            .nth(self.cursor)
+            .map(|(i, _)| i)
+            .unwrap_or_else(|| line.len());
+        let (start_byte, candidates) = self.completer.complete(&line, pos);
+        if candidates.is_empty() {
+            return;
+        }
+        let start = line[..start_byte].chars().count();
+        let replacement = if candidates.len() == 1 {
+            candidates[0].clone()
+        } else {
+            longest_common_prefix(&candidates)
+        };
+        let cursor = self.cursor;
+        let inserted_len = replacement.chars().count();
+        self.record_atomic(|buf| {
+            buf.remove_range(start, cursor);
+            for c in replacement.chars() {
+                buf.insert_char(c);
+            }
+        });
+        if candidates.len() > 1 {
+            self.completion_state = Some(CompletionState {
+                start,
+                candidates,
+                index: 0,
+                inserted_len,
+            });
+        }
+    }
+
+    /// Push a submitted statement onto the history ring.
+    /// Empty statements and immediate repeats of the last entry are not recorded.
+    fn push_history(&mut self, line: String) {
+        if line.trim().is_empty() {
+            return;
+        }
+        if self.history.back().map(|l| l.as_str()) == Some(line.as_str()) {
+            return;
+        }
+        self.history.push_back(line);
+        while self.history.len() > HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.history_index = self.history.len();
+    }
+
+    /// Replace the whole input line with `text`, place the cursor at the end, and repaint.
+    fn replace_line(&mut self, text: &str) {
+        let text = text.to_string();
+        self.record_atomic(|buf| {
+            buf.text_buffer = Rope::from_str(&text);
+            buf.cursor = buf.text_buffer.len_chars();
+        });
+        self.refresh_line();
+    }
+
+    /// Erase from the prompt to the end of the buffer, rewrite the prompt and the
+    /// text, and reposition the cursor at its logical offset.
+    fn refresh_line(&mut self) {
+        self.update_hint();
+        // Rewind by the row the *previous* repaint physically left the cursor on, not by
+        // `char_to_line(self.cursor)` — the cursor/text have usually already been mutated by
+        // the caller, so that would reflect the post-edit row instead of where the terminal's
+        // cursor actually is right now.
+        if self.cursor_row > 0 {
+            vt100::cursor_up(&mut self.output_buffer, self.cursor_row);
+        }
         write!(
             self.output_buffer,
-            "{endl}{prompt_cont}",
-            endl = vt100::CRLF,
-            prompt_cont = PROMPT_CONT
+            "{rewind}{erase}",
+            rewind = vt100::REWIND,
+            erase = vt100::ERASE_SCREEN_DOWN
         )
         .unwrap();
-        self.cursor += 1;
+
+        let line_count = self.text_buffer.len_lines();
+        let full_text: String = self.text_buffer.chars().collect();
+        let styled = self.highlighter.highlight(&full_text, self.cursor);
+        let lines: Vec<&str> = styled
+            .split(|c| c == '\n' || c == vt100::PARAGRAPH_SEPERATOR)
+            .collect();
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                write!(self.output_buffer, "{endl}", endl = vt100::CRLF).unwrap();
+            }
+            let prompt = if i == 0 { PROMPT_INIT } else { PROMPT_CONT };
+            write!(self.output_buffer, "{prompt}{line}", prompt = prompt, line = line).unwrap();
+            if i == lines.len() - 1 {
+                if let Some(hint) = &self.hint {
+                    write!(
+                        self.output_buffer,
+                        "{sgr}{hint}{reset}",
+                        sgr = vt100::SGR_HINT,
+                        hint = hint,
+                        reset = vt100::SGR_RESET
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        let target_line = self.text_buffer.char_to_line(self.cursor);
+        let trailing_lines = line_count.saturating_sub(1).saturating_sub(target_line);
+        if trailing_lines > 0 {
+            vt100::cursor_up(&mut self.output_buffer, trailing_lines);
+        }
+        write!(self.output_buffer, "{rewind}", rewind = vt100::REWIND).unwrap();
+        let line_start = self.text_buffer.line_to_char(target_line);
+        let prompt_width = if target_line == 0 {
+            PROMPT_INIT.len()
+        } else {
+            PROMPT_CONT.len()
+        };
+        vt100::cursor_right(&mut self.output_buffer, prompt_width + (self.cursor - line_start));
+        self.cursor_row = target_line;
     }
 
-    /// Insert an artificial newline as line wrap at the cursor.
-    /// The rope interprets the paragraph separator as newline.
-    /// We can therefore use the character as 'artificial' newline character and skip it during reflows.
-    /// Writes the prompt continuation string.
-    fn insert_linewrap(&mut self) {
+    /// Recall the previous history entry, saving the in-progress line on first use.
+    fn history_prev(&mut self) {
+        if self.history_index == 0 {
+            return;
+        }
+        if self.history_index == self.history.len() {
+            self.history_end = Some(self.collect_current());
+        }
+        self.history_index -= 1;
+        let entry = self.history[self.history_index].clone();
+        self.replace_line(&entry);
+    }
+
+    /// Recall the next history entry, restoring `history_end` once the bottom is reached.
+    fn history_next(&mut self) {
+        if self.history_index >= self.history.len() {
+            return;
+        }
+        self.history_index += 1;
+        if self.history_index == self.history.len() {
+            let line = self.history_end.take().unwrap_or_default();
+            self.replace_line(&line);
+        } else {
+            let entry = self.history[self.history_index].clone();
+            self.replace_line(&entry);
+        }
+    }
+
+    /// Render the current buffer as a plain string without consuming it as history.
+    fn collect_current(&self) -> String {
         self.text_buffer
-            .insert_char(self.cursor, vt100::PARAGRAPH_SEPERATOR);
+            .chars()
+            .map(|c| match c {
+                vt100::PARAGRAPH_SEPERATOR => '\n',
+                c => c,
+            })
+            .collect()
+    }
+
+    /// Begin a reverse-incremental search, saving the current line so it can be restored on cancel.
+    fn search_begin(&mut self) {
+        self.search_active = true;
+        self.search_query.clear();
+        self.search_origin = Some((self.collect_current(), self.cursor));
+        self.search_match = None;
+        self.search_painted = false;
+        self.search_render();
+    }
+
+    /// Search backwards from `before` (exclusive) for the newest entry containing `query`.
+    fn search_find(&self, before: usize) -> Option<usize> {
+        self.history
+            .iter()
+            .take(before)
+            .enumerate()
+            .rev()
+            .find(|(_, entry)| entry.contains(&self.search_query))
+            .map(|(i, _)| i)
+    }
+
+    /// Step to the next older match for the current query.
+    fn search_step(&mut self) {
+        let before = self.search_match.unwrap_or(self.history.len());
+        if let Some(idx) = self.search_find(before) {
+            self.search_match = Some(idx);
+        }
+        self.search_render();
+    }
+
+    /// Append a character to the search query and look for the newest match.
+    fn search_push_char(&mut self, c: char) {
+        self.search_query.push(c);
+        self.search_match = self.search_find(self.history.len());
+        self.search_render();
+    }
+
+    /// Remove the last character from the search query and re-search.
+    fn search_pop_char(&mut self) {
+        self.search_query.pop();
+        self.search_match = self.search_find(self.history.len());
+        self.search_render();
+    }
+
+    /// Repaint the `(reverse-i-search)` status line with the current query and match.
+    ///
+    /// The status line replaces whatever was on screen when the search began, which may be a
+    /// multi-line or wrapped buffer. Only the very first paint moves up, by `cursor_row`, to the
+    /// top of that buffer; every later repaint is already sitting on the single status line and
+    /// rewinds in place.
+    fn search_render(&mut self) {
+        let matched = self
+            .search_match
+            // History entries may be multi-line statements (real '\n's, inserted via
+            // `insert_newline`); collapse to a single line so the match can't spill onto rows
+            // the next repaint's `REWIND`+`ERASE_SCREEN_DOWN` won't clear.
+            .map(|i| self.history[i].replace('\n', " "))
+            .unwrap_or_default();
+        if !self.search_painted {
+            // Same physical-row tracking as `refresh_line`: rewind by where the last repaint
+            // actually left the cursor, not by recomputing from the (untouched) buffer.
+            if self.cursor_row > 0 {
+                vt100::cursor_up(&mut self.output_buffer, self.cursor_row);
+            }
+            self.cursor_row = 0;
+            self.search_painted = true;
+        }
         write!(
             self.output_buffer,
-            "{endl}{prompt_cont}",
-            endl = vt100::CRLF,
-            prompt_cont = PROMPT_CONT
+            "{rewind}{erase}{prompt}{query}': {matched}",
+            rewind = vt100::REWIND,
+            erase = vt100::ERASE_SCREEN_DOWN,
+            prompt = SEARCH_PROMPT,
+            query = self.search_query,
+            matched = matched
         )
         .unwrap();
+    }
+
+    /// Accept the current search match as the input line.
+    fn search_accept(&mut self) {
+        let line = self
+            .search_match
+            .map(|i| self.history[i].clone())
+            .unwrap_or_default();
+        self.search_active = false;
+        self.search_origin = None;
+        self.search_painted = false;
+        self.replace_line(&line);
+    }
+
+    /// Cancel the search and restore the line as it was before it started.
+    fn search_cancel(&mut self) {
+        self.search_active = false;
+        self.search_painted = false;
+        let (line, cursor) = self.search_origin.take().unwrap_or_default();
+        self.record_atomic(|buf| {
+            buf.text_buffer = Rope::from_str(&line);
+            buf.cursor = cursor.min(buf.text_buffer.len_chars());
+        });
+        self.refresh_line();
+    }
+
+    /// Char offset of the start of the previous word, for `Ctrl+W`/`Alt+B`.
+    fn word_left(&self) -> usize {
+        let text = self.collect_current();
+        let cursor_byte = char_to_byte(&text, self.cursor);
+        let start_byte = text[..cursor_byte]
+            .unicode_word_indices()
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        byte_to_char(&text, start_byte)
+    }
+
+    /// Char offset of the end of the next word, for `Alt+F`.
+    fn word_right(&self) -> usize {
+        let text = self.collect_current();
+        let cursor_byte = char_to_byte(&text, self.cursor);
+        let end_byte = text[cursor_byte..]
+            .unicode_word_indices()
+            .next()
+            .map(|(i, w)| cursor_byte + i + w.len())
+            .unwrap_or_else(|| text.len());
+        byte_to_char(&text, end_byte)
+    }
+
+    /// Char offset of the start of the current logical line (bounded by real newlines).
+    fn logical_line_start(&self) -> usize {
+        let mut iter = self.text_buffer.chars_at(self.cursor);
+        let mut pos = self.cursor;
+        while pos > 0 {
+            match iter.prev() {
+                Some('\n') => break,
+                Some(_) => pos -= 1,
+                None => break,
+            }
+        }
+        pos
+    }
+
+    /// Char offset of the end of the current logical line (bounded by real newlines).
+    fn logical_line_end(&self) -> usize {
+        let mut iter = self.text_buffer.chars_at(self.cursor);
+        let mut pos = self.cursor;
+        loop {
+            match iter.next() {
+                Some('\n') => break,
+                Some(_) => pos += 1,
+                None => break,
+            }
+        }
+        pos
+    }
+
+    /// Move the cursor to `pos` and repaint.
+    fn move_cursor_to(&mut self, pos: usize) {
+        self.cursor = pos;
+        self.refresh_line();
+    }
+
+    /// Clear UI state left over from a previous key press (completion cycling,
+    /// yank rotation, kill-ring merging) unless the incoming key continues that
+    /// same command. Called once per key press, before dispatch.
+    fn clear_volatile_state(&mut self, key_code: u32, ctrl: bool, alt: bool) {
+        if key_code != vt100::KEY_TAB {
+            self.completion_state = None;
+        }
+        if !(key_code == vt100::KEY_Y && alt) {
+            self.last_yank = None;
+        }
+        let is_kill_key = ctrl && matches!(key_code, vt100::KEY_W | vt100::KEY_U | vt100::KEY_K);
+        if !is_kill_key {
+            self.last_kill_dir = None;
+        }
+    }
+
+    /// Delete `start..end`, push the removed text onto the kill ring, and repaint.
+    /// Consecutive kills in the same direction append to the same ring entry instead
+    /// of creating a new one.
+    fn kill(&mut self, start: usize, end: usize, dir: KillDirection) {
+        if start == end {
+            return;
+        }
+        let text: String = self.text_buffer.slice(start..end).chars().collect();
+        self.remove_range(start, end);
+        match (self.last_kill_dir, self.kill_ring.back_mut()) {
+            (Some(last_dir), Some(last_entry)) if last_dir == dir => match dir {
+                KillDirection::Backward => last_entry.insert_str(0, &text),
+                KillDirection::Forward => last_entry.push_str(&text),
+            },
+            _ => {
+                self.kill_ring.push_back(text);
+                while self.kill_ring.len() > KILL_RING_LIMIT {
+                    self.kill_ring.pop_front();
+                }
+            }
+        }
+        self.last_kill_dir = Some(dir);
+        self.yank_pointer = 0;
+        self.refresh_line();
+    }
+
+    /// Kill the word before the cursor (`Ctrl+W`).
+    fn kill_word_left(&mut self) {
+        let start = self.word_left();
+        self.kill(start, self.cursor, KillDirection::Backward);
+    }
+
+    /// Kill from the cursor to the start of the current logical line (`Ctrl+U`).
+    fn kill_to_line_start(&mut self) {
+        let start = self.logical_line_start();
+        self.kill(start, self.cursor, KillDirection::Backward);
+    }
+
+    /// Kill from the cursor to the end of the current logical line (`Ctrl+K`).
+    fn kill_to_line_end(&mut self) {
+        let end = self.logical_line_end();
+        self.kill(self.cursor, end, KillDirection::Forward);
+    }
+
+    /// Insert the most recent kill ring entry at the cursor (`Ctrl+Y`).
+    fn yank(&mut self) {
+        if self.kill_ring.is_empty() {
+            return;
+        }
+        self.yank_pointer = 0;
+        let text = self.kill_ring[self.kill_ring.len() - 1].clone();
+        let start = self.cursor;
+        self.record_atomic(|buf| {
+            for c in text.chars() {
+                buf.insert_char(c);
+            }
+        });
+        self.last_yank = Some((start, self.cursor));
+        self.last_kill_dir = None;
+    }
+
+    /// Rotate the yank pointer and replace the just-yanked text with the previous
+    /// ring entry (`Alt+Y`, must immediately follow `Ctrl+Y`).
+    fn yank_rotate(&mut self) {
+        let (start, end) = match self.last_yank {
+            Some(range) => range,
+            None => return,
+        };
+        if self.kill_ring.is_empty() {
+            return;
+        }
+        self.yank_pointer = (self.yank_pointer + 1) % self.kill_ring.len();
+        let index = self.kill_ring.len() - 1 - self.yank_pointer;
+        let text = self.kill_ring[index].clone();
+        self.record_atomic(|buf| {
+            buf.remove_range(start, end);
+            for c in text.chars() {
+                buf.insert_char(c);
+            }
+        });
+        self.last_yank = Some((start, self.cursor));
+    }
+
+    /// Insert a newline at the cursor and repaint.
+    fn insert_newline(&mut self) {
+        let pos = self.cursor;
+        self.text_buffer.insert_char(pos, '\n');
+        self.cursor += 1;
+        if self.undo_recording {
+            self.push_undo_insert(pos, '\n');
+        }
+        self.refresh_line();
+    }
+
+    /// Insert an artificial newline as line wrap at the cursor.
+    /// The rope interprets the paragraph separator as newline.
+    /// We can therefore use the character as 'artificial' newline character and skip it during reflows.
+    fn insert_linewrap(&mut self) {
+        let pos = self.cursor;
+        self.text_buffer.insert_char(pos, vt100::PARAGRAPH_SEPERATOR);
         self.cursor += 1;
+        if self.undo_recording {
+            self.push_undo_insert(pos, vt100::PARAGRAPH_SEPERATOR);
+        }
     }
 
-    /// Insert a character at the cursor.
-    /// Insert a single character at the cursor.
-    /// Takes care of line wrapping if necessary
+    /// Insert a character at the cursor, wrapping the line if necessary, and repaint.
     fn insert_char(&mut self, c: char) {
         let line_id = self.text_buffer.char_to_line(self.cursor);
         let line = match self.text_buffer.lines_at(line_id).next() {
@@ -106,17 +1076,97 @@ impl PromptBuffer {
         if (PROMPT_WIDTH + line.len_chars()) >= self.terminal_width {
             self.insert_linewrap();
         }
-        self.text_buffer.insert_char(self.cursor, c);
+        let pos = self.cursor;
+        self.text_buffer.insert_char(pos, c);
         self.cursor += 1;
-        write!(self.output_buffer, "{}", self.text_buffer.len_lines()).unwrap();
+        if self.undo_recording {
+            self.push_undo_insert(pos, c);
+        }
+        self.refresh_line();
     }
 
     /// Process key event
     pub fn consume(&mut self, event: KeyboardEvent) {
+        if self.search_active {
+            match event.key_code() {
+                vt100::KEY_ENTER => self.search_accept(),
+                vt100::KEY_ESCAPE => self.search_cancel(),
+                vt100::KEY_BACKSPACE => {
+                    if self.search_query.is_empty() {
+                        self.search_cancel();
+                    } else {
+                        self.search_pop_char();
+                    }
+                }
+                vt100::KEY_R if event.ctrl_key() => self.search_step(),
+                vt100::KEY_G if event.ctrl_key() => self.search_cancel(),
+                _ => {
+                    // `event.key()` is a multi-char DOM name ("ArrowUp", "Tab", "Delete", ...)
+                    // for keys without a printable glyph; only a single char means a typed key.
+                    if !event.ctrl_key()
+                        && !event.alt_key()
+                        && !event.meta_key()
+                        && event.key().chars().count() == 1
+                    {
+                        let c = event.key().chars().next().unwrap();
+                        self.search_push_char(c);
+                    }
+                }
+            }
+            return;
+        }
+
+        self.clear_volatile_state(event.key_code(), event.ctrl_key(), event.alt_key());
+
         match event.key_code() {
             vt100::KEY_ENTER => {
                 self.insert_newline();
             }
+            vt100::KEY_TAB => {
+                self.complete();
+            }
+            vt100::KEY_W if event.ctrl_key() => {
+                self.kill_word_left();
+            }
+            vt100::KEY_U if event.ctrl_key() => {
+                self.kill_to_line_start();
+            }
+            vt100::KEY_K if event.ctrl_key() => {
+                self.kill_to_line_end();
+            }
+            vt100::KEY_Y if event.ctrl_key() => {
+                self.yank();
+            }
+            vt100::KEY_Y if event.alt_key() => {
+                self.yank_rotate();
+            }
+            vt100::KEY_B if event.alt_key() => {
+                let pos = self.word_left();
+                self.move_cursor_to(pos);
+            }
+            vt100::KEY_F if event.alt_key() => {
+                let pos = self.word_right();
+                self.move_cursor_to(pos);
+            }
+            vt100::KEY_A if event.ctrl_key() => {
+                let pos = self.logical_line_start();
+                self.move_cursor_to(pos);
+            }
+            vt100::KEY_E if event.ctrl_key() => {
+                if !self.accept_hint() {
+                    let pos = self.logical_line_end();
+                    self.move_cursor_to(pos);
+                }
+            }
+            vt100::KEY_UNDERSCORE if event.ctrl_key() => {
+                self.undo();
+            }
+            vt100::KEY_Z if event.ctrl_key() => {
+                self.undo();
+            }
+            vt100::KEY_Z if event.alt_key() => {
+                self.redo();
+            }
             vt100::KEY_BACKSPACE => {
                 let mut iter = self.text_buffer.chars_at(self.cursor);
                 match iter.prev() {
@@ -131,69 +1181,44 @@ impl PromptBuffer {
 
                             // In all other cases, just remove the character
                             _ => {
-                                write!(self.output_buffer, "{}", '\u{0008}').unwrap();
-                                self.text_buffer.remove((self.cursor - 1)..(self.cursor));
-                                self.cursor -= 1;
+                                self.remove_range(self.cursor - 1, self.cursor);
+                                self.refresh_line();
                             }
                         }
                     }
                     None => return,
                 }
             }
-            vt100::KEY_ARROW_UP | vt100::KEY_ARROW_DOWN => return,
+            vt100::KEY_R if event.ctrl_key() => {
+                self.search_begin();
+            }
+            vt100::KEY_ARROW_UP => {
+                self.history_prev();
+            }
+            vt100::KEY_ARROW_DOWN => {
+                self.history_next();
+            }
+            // A plain cursor move still needs a full repaint: `refresh_line` is what erases
+            // any hint painted after the old cursor position and repaints it (or not) for
+            // the new one, so a stale dim suffix doesn't linger after moving off end-of-line.
             vt100::KEY_ARROW_LEFT => {
                 let mut iter = self.text_buffer.chars_at(self.cursor);
                 match iter.prev() {
-                    Some(c) => {
-                        // Move to end of previous line?
-                        if c == '\n' {
-                            let line_id = self.text_buffer.char_to_line(self.cursor - 1);
-                            let line = self.text_buffer.line(line_id);
-                            write!(
-                                self.output_buffer,
-                                "{rewind}{cursor_up}",
-                                rewind = vt100::REWIND,
-                                cursor_up = vt100::CURSOR_UP
-                            )
-                            .unwrap();
-                            vt100::cursor_right(&mut self.output_buffer, line.len_chars());
-                        } else {
-                            write!(
-                                self.output_buffer,
-                                "{cursor_left}",
-                                cursor_left = vt100::CURSOR_LEFT
-                            )
-                            .unwrap()
-                        }
+                    Some(_) => {
                         self.cursor -= 1;
+                        self.refresh_line();
                     }
                     // Reached beginning of input
                     None => return,
                 }
             }
+            vt100::KEY_ARROW_RIGHT if self.hint.is_some() && self.accept_hint() => {}
             vt100::KEY_ARROW_RIGHT => {
                 let mut iter = self.text_buffer.chars_at(self.cursor);
                 match iter.next() {
-                    Some(c) => {
-                        // Move to beginning of previous line?
-                        if c == '\n' {
-                            write!(
-                                self.output_buffer,
-                                "{rewind}{cursor_down}",
-                                rewind = vt100::REWIND,
-                                cursor_down = vt100::CURSOR_DOWN
-                            )
-                            .unwrap();
-                            vt100::cursor_right(&mut self.output_buffer, PROMPT_INIT.len());
-                        } else {
-                            write!(
-                                self.output_buffer,
-                                "{cursor_right}",
-                                cursor_right = vt100::CURSOR_RIGHT
-                            )
-                            .unwrap()
-                        }
+                    Some(_) => {
                         self.cursor += 1;
+                        self.refresh_line();
                     }
                     // Reached end of input
                     None => return,
@@ -207,3 +1232,74 @@ impl PromptBuffer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a `PromptBuffer` with `text` already in the rope and the cursor at `cursor`.
+    fn buffer_with(text: &str, cursor: usize) -> PromptBuffer {
+        let mut buf = PromptBuffer::default();
+        buf.text_buffer = Rope::from_str(text);
+        buf.cursor = cursor;
+        buf
+    }
+
+    #[test]
+    fn longest_common_prefix_stops_at_first_divergence() {
+        let candidates = vec!["SELECT".to_string(), "SET".to_string(), "SELECT".to_string()];
+        assert_eq!(longest_common_prefix(&candidates), "SE");
+    }
+
+    #[test]
+    fn longest_common_prefix_of_a_single_candidate_is_itself() {
+        let candidates = vec!["SELECT".to_string()];
+        assert_eq!(longest_common_prefix(&candidates), "SELECT");
+    }
+
+    #[test]
+    fn word_left_stops_at_the_previous_word_boundary() {
+        let buf = buffer_with("select foo from bar", 16);
+        assert_eq!(buf.word_left(), 11);
+    }
+
+    #[test]
+    fn word_right_stops_at_the_next_word_boundary() {
+        let buf = buffer_with("select foo from bar", 7);
+        assert_eq!(buf.word_right(), 10);
+    }
+
+    #[test]
+    fn consecutive_kills_in_the_same_direction_merge() {
+        let mut buf = buffer_with("abc def xyz", 0);
+        buf.kill(0, 4, KillDirection::Forward); // kills "abc "
+        buf.clear_volatile_state(vt100::KEY_K, true, false); // the next Ctrl+K itself
+        buf.kill(0, 4, KillDirection::Forward); // kills "def "
+        assert_eq!(
+            buf.kill_ring,
+            VecDeque::from(vec!["abc def ".to_string()])
+        );
+    }
+
+    #[test]
+    fn non_consecutive_kills_in_the_same_direction_stay_separate() {
+        let mut buf = buffer_with("abc def xyz 123", 0);
+        buf.kill(0, 7, KillDirection::Forward); // kills "abc def"
+        // An unrelated key press in between (e.g. moving the cursor) must break the run.
+        buf.clear_volatile_state(vt100::KEY_ARROW_LEFT, false, false);
+        buf.kill(1, 8, KillDirection::Forward); // kills "xyz 123" from " xyz 123"
+        assert_eq!(
+            buf.kill_ring,
+            VecDeque::from(vec!["abc def".to_string(), "xyz 123".to_string()])
+        );
+    }
+
+    #[test]
+    fn highlight_colors_keywords_numbers_strings_and_comments() {
+        let styled = SqlHighlighter.highlight("SELECT 1 FROM 'x' -- done", 0);
+        assert!(styled.contains(vt100::SGR_KEYWORD));
+        assert!(styled.contains(vt100::SGR_NUMBER));
+        assert!(styled.contains(vt100::SGR_STRING));
+        assert!(styled.contains(vt100::SGR_COMMENT));
+    }
+}